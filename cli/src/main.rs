@@ -4,6 +4,9 @@ use log::LevelFilter;
 use nix::unistd::{getuid, setegid, seteuid, Gid, Uid};
 use structopt::StructOpt;
 use usbctl::actions::{Action, Filter};
+use usbctl::matcher::{MatchMode, Matcher};
+use usbctl::policy::Policy;
+use usbctl::watch::Watcher;
 
 /// USB devices management.
 #[derive(StructOpt)]
@@ -55,6 +58,60 @@ enum Command {
         #[structopt(flatten)]
         value: SearchOptions,
     },
+
+    /// Watches for hotplug events and applies an action to matching devices
+    /// as they are plugged in.
+    Watch {
+        /// Action to apply to every matching device that shows up.
+        #[structopt(possible_values = &["on", "off", "toggle"])]
+        action: ActionArg,
+
+        #[structopt(flatten)]
+        value: SearchOptions,
+    },
+
+    /// Evaluates the rules file and binds/unbinds devices accordingly.
+    Enforce {
+        /// Path to the rules file. Defaults to `/etc/usbctl/rules.toml`.
+        #[structopt(long)]
+        config: Option<std::path::PathBuf>,
+
+        /// Enables "dry run" mode, when no real actions are performed.
+        #[structopt(long)]
+        dry_run: bool,
+    },
+
+    /// Generates a shell completion script.
+    Completions {
+        /// Shell to generate completions for.
+        shell: structopt::clap::Shell,
+    },
+
+    /// Prints currently attached device ports and names, one per line.
+    ///
+    /// Not meant to be run directly; the bash script generated by
+    /// `completions bash` shells out to this so that `usbctl off <TAB>`
+    /// completes against devices that are actually plugged in.
+    #[structopt(name = "__complete-devices", setting = structopt::clap::AppSettings::Hidden)]
+    CompleteDevices,
+}
+
+/// A CLI-friendly wrapper around [Action], so it can be parsed from an
+/// argument string.
+#[derive(Debug, Clone, Copy)]
+struct ActionArg(Action);
+
+impl std::str::FromStr for ActionArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "on" => Ok(ActionArg(Action::On)),
+            "off" => Ok(ActionArg(Action::Off)),
+            "toggle" => Ok(ActionArg(Action::Toggle)),
+            other => Err(format!("Unknown action {:?}, expected on/off/toggle", other)),
+        }
+    }
 }
 
 #[derive(StructOpt)]
@@ -63,12 +120,32 @@ struct SearchOptions {
     search: Vec<String>,
 
     /// Matches only when port or name matches the search string exactly.
-    #[structopt(short, long)]
+    #[structopt(short, long, conflicts_with_all(&["regex", "glob"]))]
     exact: bool,
 
+    /// Treats search strings as regular expressions.
+    #[structopt(long, conflicts_with("glob"))]
+    regex: bool,
+
+    /// Treats search strings as shell globs (`*` and `?`).
+    #[structopt(long)]
+    glob: bool,
+
     /// Enables "dry run" mode, when no real actions are performed.
     #[structopt(long)]
     dry_run: bool,
+
+    /// Matches only devices with the given vendor id, e.g. `046d`.
+    #[structopt(long)]
+    vid: Option<String>,
+
+    /// Matches only devices with the given product id, e.g. `c52b`.
+    #[structopt(long)]
+    pid: Option<String>,
+
+    /// Matches only the device with the given serial number.
+    #[structopt(long)]
+    serial: Option<String>,
 }
 
 fn main() {
@@ -179,9 +256,7 @@ fn run(options: Options) -> anyhow::Result<()> {
                 .context("Collecting devices")?;
             log::info!("Found {} device(s):", devices.len());
             for device in devices {
-                if !options.allow_host
-                    && (device.name.contains("Host") || device.name.contains("host"))
-                {
+                if !options.allow_host && device.is_host_or_hub() {
                     continue;
                 }
                 log::info!("{}", device);
@@ -190,55 +265,193 @@ fn run(options: Options) -> anyhow::Result<()> {
         Command::On { value } => apply(Action::On, value, options.allow_host)?,
         Command::Off { value } => apply(Action::Off, value, options.allow_host)?,
         Command::Toggle { value } => apply(Action::Toggle, value, options.allow_host)?,
+        Command::Watch { action, value } => watch(action.0, value, options.allow_host)?,
+        Command::Enforce { config, dry_run } => {
+            enforce(config, dry_run, options.allow_host)?
+        }
+        Command::Completions { shell } => print_completions(shell),
+        Command::CompleteDevices => complete_devices()?,
     }
     Ok(())
 }
 
-/// Applies an action to filtered devices.
-fn apply(
-    action: Action,
-    SearchOptions {
-        search,
-        exact,
-        dry_run,
-    }: SearchOptions,
+/// Prints a shell completion script for `shell` to stdout.
+///
+/// For bash, this appends a small dynamic-completion override on top of
+/// clap's static script, so `usbctl off <TAB>` shells out to the hidden
+/// `__complete-devices` subcommand and completes against devices that are
+/// actually plugged in. clap has no dynamic completion hooks for the other
+/// shells, so they only get the stock static (subcommand/flag) script.
+fn print_completions(shell: structopt::clap::Shell) {
+    let bin_name = env!("CARGO_PKG_NAME");
+    Options::clap().gen_completions_to(bin_name, shell, &mut std::io::stdout());
+    if shell == structopt::clap::Shell::Bash {
+        print!(
+            "\n_{bin_name}_device_complete() {{\n    case \"${{COMP_WORDS[1]}}\" in\n        \
+             on|off|toggle|watch)\n            local IFS=$'\\n'\n            \
+             COMPREPLY=( $(compgen -W \"$({bin_name} __complete-devices 2>/dev/null)\" -- \
+             \"${{COMP_WORDS[COMP_CWORD]}}\") )\n            \
+             ;;\n        *)\n            _{bin_name} \"$@\"\n            ;;\n    esac\n}}\n\
+             complete -F _{bin_name}_device_complete -o bashdefault -o default {bin_name}\n",
+            bin_name = bin_name
+        );
+    }
+}
+
+/// Prints the port and name of every currently attached device, one
+/// candidate per line, for shell completion scripts to filter against.
+///
+/// Each device's port and name are separate candidates, but multi-word names
+/// (e.g. "Logitech USB Optical Mouse") must round-trip as a single
+/// completion word; the bash completion script scopes `IFS` to a newline
+/// around its `compgen -W` call so it only splits on the newlines below, not
+/// on embedded spaces.
+fn complete_devices() -> anyhow::Result<()> {
+    for device in usbctl::device::discover().context("Looking for devices")? {
+        let device = device.context("Fetching a device")?;
+        println!("{}", device.port.to_string_lossy());
+        println!("{}", device.name);
+    }
+    Ok(())
+}
+
+/// Discovers all devices and binds/unbinds them according to the policy
+/// loaded from `config` (or [usbctl::policy::DEFAULT_PATH] if unset).
+fn enforce(
+    config: Option<std::path::PathBuf>,
+    dry_run: bool,
     allow_host: bool,
 ) -> anyhow::Result<()> {
+    let config =
+        config.unwrap_or_else(|| std::path::PathBuf::from(usbctl::policy::DEFAULT_PATH));
+    let policy = Policy::load(&config)
+        .with_context(|| format!("Loading the rules file {}", config.display()))?;
+    let devices = usbctl::device::discover()
+        .context("Looking for devices")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Collecting devices")?;
+    let (mut to_allow, mut to_deny): (
+        Vec<Result<usbctl::device::Device, usbctl::device::DiscoveryError>>,
+        Vec<Result<usbctl::device::Device, usbctl::device::DiscoveryError>>,
+    ) = (Vec::new(), Vec::new());
+    for device in devices {
+        if !allow_host && device.is_host_or_hub() {
+            continue;
+        }
+        match (policy.evaluate(&device), device.online) {
+            (usbctl::policy::Effect::Allow, usbctl::device::Status::Offline) => {
+                to_allow.push(Ok(device))
+            }
+            (usbctl::policy::Effect::Deny, usbctl::device::Status::Online) => {
+                to_deny.push(Ok(device))
+            }
+            // Already in the policy's desired state: nothing to do, and
+            // running it through `Apply` would only produce a "refusing to
+            // turn on/off" warning for an already-compliant device on every
+            // periodic run.
+            (usbctl::policy::Effect::Allow, usbctl::device::Status::Online)
+            | (usbctl::policy::Effect::Deny, usbctl::device::Status::Offline) => {}
+        }
+    }
+    usbctl::actions::Apply::new(to_allow)
+        .dry_run(dry_run)
+        .run(Action::On)?;
+    usbctl::actions::Apply::new(to_deny)
+        .dry_run(dry_run)
+        .run(Action::Off)?;
+    Ok(())
+}
+
+/// Watches for hotplug events and applies `action` to every newly attached
+/// device that matches the given search options.
+fn watch(action: Action, value: SearchOptions, allow_host: bool) -> anyhow::Result<()> {
+    let dry_run = value.dry_run;
+    let filter = DeviceMatch::new(value, allow_host)?;
+    let watcher =
+        Watcher::new(filter, action, dry_run).context("Setting up a hotplug watcher")?;
+    watcher.run().context("Watching for hotplug events")?;
+    Ok(())
+}
+
+/// Applies an action to filtered devices.
+fn apply(action: Action, value: SearchOptions, allow_host: bool) -> anyhow::Result<()> {
+    let dry_run = value.dry_run;
     usbctl::actions::Apply::new(usbctl::device::discover().context("Looking for devices")?)
-        .filter(DeviceMatch::new(search, exact, allow_host))
+        .filter(DeviceMatch::new(value, allow_host)?)
         .dry_run(dry_run)
         .run(action)?;
     Ok(())
 }
 
-/// A simple filter that checks if a device matches any of the search strings.
+/// A filter that checks if a device matches the search terms and the
+/// optional vid/pid/serial constraints.
 struct DeviceMatch {
-    search: Vec<String>,
-    exact: bool,
+    search_empty: bool,
+    matcher: Matcher,
+    vid: Option<String>,
+    pid: Option<String>,
+    serial: Option<String>,
     allow_host: bool,
 }
 
 impl DeviceMatch {
-    /// Initializes a [DeviceMatch].
-    fn new(search: Vec<String>, exact: bool, allow_host: bool) -> Self {
-        Self {
+    /// Initializes a [DeviceMatch] from the parsed [SearchOptions].
+    fn new(
+        SearchOptions {
             search,
             exact,
+            regex,
+            glob,
+            dry_run: _,
+            vid,
+            pid,
+            serial,
+        }: SearchOptions,
+        allow_host: bool,
+    ) -> anyhow::Result<Self> {
+        let mode = match (exact, regex, glob) {
+            (true, _, _) => MatchMode::Exact,
+            (_, true, _) => MatchMode::Regex,
+            (_, _, true) => MatchMode::Glob,
+            (false, false, false) => MatchMode::Contains,
+        };
+        let search_empty = search.is_empty();
+        let matcher = Matcher::new(search, mode).context("Compiling search terms")?;
+        Ok(Self {
+            search_empty,
+            matcher,
+            vid,
+            pid,
+            serial,
             allow_host,
-        }
+        })
     }
 }
 
 impl Filter for DeviceMatch {
     fn filter(&mut self, device: &usbctl::device::Device) -> bool {
-        if self.search.is_empty()
-            || !self.allow_host && (device.name.contains("Host") || device.name.contains("host"))
-        {
-            false
+        if !self.allow_host && device.is_host_or_hub() {
+            return false;
+        }
+        if let Some(vid) = &self.vid {
+            if !device.matches_vendor_id(vid) {
+                return false;
+            }
+        }
+        if let Some(pid) = &self.pid {
+            if !device.matches_product_id(pid) {
+                return false;
+            }
+        }
+        if let Some(serial) = &self.serial {
+            if !device.matches_serial(serial) {
+                return false;
+            }
+        }
+        if self.search_empty {
+            self.vid.is_some() || self.pid.is_some() || self.serial.is_some()
         } else {
-            self.search
-                .iter()
-                .any(|search| device.matches(search, self.exact))
+            self.matcher.matches(device)
         }
     }
 }
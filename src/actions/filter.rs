@@ -57,6 +57,16 @@ pub trait FilterExt: Filter {
     }
 }
 
+impl<F> Filter for &mut F
+where
+    F: Filter,
+{
+    #[inline]
+    fn filter(&mut self, device: &Device) -> bool {
+        (*self).filter(device)
+    }
+}
+
 /// A filter that combines two filters.
 pub struct ChainFilter<First, Second> {
     first: First,
@@ -46,8 +46,22 @@ pub struct Device {
     pub name: String,
     /// Whether device is online.
     pub online: Status,
+    /// Vendor id, e.g. `046d`, read from the sysfs `idVendor` attribute.
+    pub vendor_id: Option<String>,
+    /// Product id, e.g. `c52b`, read from the sysfs `idProduct` attribute.
+    pub product_id: Option<String>,
+    /// Manufacturer name, read from the sysfs `manufacturer` attribute.
+    pub manufacturer: Option<String>,
+    /// Serial number, read from the sysfs `serial` attribute.
+    pub serial: Option<String>,
+    /// USB device class, read from the sysfs `bDeviceClass` attribute, e.g.
+    /// `09` for hubs (including root hubs exposed by host controllers).
+    pub device_class: Option<String>,
 }
 
+/// USB device class code for hubs, per the USB spec.
+const HUB_DEVICE_CLASS: &str = "09";
+
 /// Device status change error.
 #[derive(Debug, Snafu)]
 pub enum StatusError {
@@ -93,6 +107,43 @@ impl Device {
             port.contains(search) || self.name.contains(search)
         }
     }
+
+    /// Checks if the vendor id (sysfs `idVendor`) matches `vid`, case-insensitively.
+    pub fn matches_vendor_id(&self, vid: &str) -> bool {
+        attr_eq(&self.vendor_id, vid)
+    }
+
+    /// Checks if the product id (sysfs `idProduct`) matches `pid`, case-insensitively.
+    pub fn matches_product_id(&self, pid: &str) -> bool {
+        attr_eq(&self.product_id, pid)
+    }
+
+    /// Checks if the serial number (sysfs `serial`) matches `serial` exactly.
+    pub fn matches_serial(&self, serial: &str) -> bool {
+        self.serial.as_deref() == Some(serial)
+    }
+
+    /// Checks whether this device is a host controller's root hub or a
+    /// regular external hub, rather than a "real" peripheral.
+    ///
+    /// Checks the sysfs `bDeviceClass` attribute (`09` identifies hubs, see
+    /// the USB spec) and, independently of it, whether the name contains
+    /// "host" or "hub" (case-insensitively), so a hub is never missed just
+    /// because the hub class attribute is absent. Either check alone is
+    /// enough.
+    pub fn is_host_or_hub(&self) -> bool {
+        self.device_class.as_deref() == Some(HUB_DEVICE_CLASS)
+            || ["host", "hub"]
+                .iter()
+                .any(|needle| self.name.to_lowercase().contains(needle))
+    }
+}
+
+/// Compares an optional sysfs attribute against `expected`, case-insensitively.
+fn attr_eq(attr: &Option<String>, expected: &str) -> bool {
+    attr.as_deref()
+        .map(|value| value.eq_ignore_ascii_case(expected))
+        .unwrap_or(false)
 }
 
 impl fmt::Display for Device {
@@ -104,7 +155,17 @@ impl fmt::Display for Device {
             name = self.name,
             port = port,
             active = self.online
-        )
+        )?;
+        if let (Some(vendor_id), Some(product_id)) = (&self.vendor_id, &self.product_id) {
+            write!(f, " [{}:{}]", vendor_id, product_id)?;
+        }
+        if let Some(manufacturer) = &self.manufacturer {
+            write!(f, " by {}", manufacturer)?;
+        }
+        if let Some(serial) = &self.serial {
+            write!(f, " (serial {})", serial)?;
+        }
+        Ok(())
     }
 }
 
@@ -193,7 +254,27 @@ pub fn discover() -> Result<impl Iterator<Item = Result<Device, DiscoveryError>>
                 port: port.into(),
                 name: contents.trim().into(),
                 online: Status::from_bool(driver.exists()),
+                vendor_id: read_optional_attr(&path.join("idVendor")),
+                product_id: read_optional_attr(&path.join("idProduct")),
+                manufacturer: read_optional_attr(&path.join("manufacturer")),
+                serial: read_optional_attr(&path.join("serial")),
+                device_class: read_optional_attr(&path.join("bDeviceClass")),
             }))
         })
         .filter_map(Result::transpose))
 }
+
+/// Reads an optional sysfs attribute file, returning `None` when it doesn't
+/// exist or is empty.
+///
+/// Unlike `product`, these attributes aren't present on every device (e.g.
+/// hubs have no `serial`), so a missing file isn't an error.
+fn read_optional_attr(path: &Path) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    let contents = contents.trim();
+    if contents.is_empty() {
+        None
+    } else {
+        Some(contents.to_owned())
+    }
+}
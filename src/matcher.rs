@@ -0,0 +1,153 @@
+//! Pattern-based matching of a set of search terms against a [Device].
+//!
+//! `Device::matches` only offers a per-term substring/exact check; a
+//! [Matcher] compiles a whole set of terms once, so callers like the CLI can
+//! match a single device against many regexes or globs without recompiling
+//! on every device.
+
+use serde::Deserialize;
+use snafu::{ResultExt, Snafu};
+
+use crate::device::Device;
+
+/// How a set of search terms is matched against a device's port and name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// A term matches if the port or name contains it.
+    Contains,
+    /// A term matches if the port or name equals it exactly.
+    Exact,
+    /// Terms are regular expressions.
+    Regex,
+    /// Terms are shell globs (`*` and `?`).
+    Glob,
+}
+
+impl Default for MatchMode {
+    fn default() -> Self {
+        MatchMode::Contains
+    }
+}
+
+/// A compiled set of search terms, ready to be tested against devices.
+pub enum Matcher {
+    /// Plain substring or exact-equality terms.
+    Literal { terms: Vec<String>, exact: bool },
+    /// Terms compiled into a single regex set (used for both [MatchMode::Regex]
+    /// and [MatchMode::Glob], the latter translated to regex first).
+    Pattern(regex::RegexSet),
+}
+
+/// Error compiling a [Matcher].
+#[derive(Debug, Snafu)]
+pub enum Error {
+    /// One of the search terms is not a valid regular expression.
+    #[snafu(display("Invalid regex search term"))]
+    Regex { source: regex::Error },
+}
+
+impl Matcher {
+    /// Compiles `terms` under the given `mode`.
+    pub fn new(terms: Vec<String>, mode: MatchMode) -> Result<Self, Error> {
+        match mode {
+            MatchMode::Contains => Ok(Matcher::Literal {
+                terms,
+                exact: false,
+            }),
+            MatchMode::Exact => Ok(Matcher::Literal { terms, exact: true }),
+            MatchMode::Regex => {
+                Ok(Matcher::Pattern(regex::RegexSet::new(&terms).context(Regex)?))
+            }
+            MatchMode::Glob => {
+                let patterns: Vec<_> = terms.iter().map(|term| glob_to_regex(term)).collect();
+                Ok(Matcher::Pattern(
+                    regex::RegexSet::new(&patterns).context(Regex)?,
+                ))
+            }
+        }
+    }
+
+    /// Checks if `device`'s port or name matches any of the compiled terms.
+    pub fn matches(&self, device: &Device) -> bool {
+        match self {
+            Matcher::Literal { terms, exact } => {
+                terms.iter().any(|term| device.matches(term, *exact))
+            }
+            Matcher::Pattern(set) => {
+                let port = device.port.to_string_lossy();
+                set.is_match(&port) || set.is_match(&device.name)
+            }
+        }
+    }
+}
+
+/// Translates a shell glob (`*`, `?`, and literal characters) into an anchored
+/// regex.
+fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{Device, Status};
+    use std::ffi::OsString;
+
+    fn device(port: &str, name: &str) -> Device {
+        Device {
+            port: OsString::from(port),
+            name: name.to_owned(),
+            online: Status::Online,
+            vendor_id: None,
+            product_id: None,
+            manufacturer: None,
+            serial: None,
+            device_class: None,
+        }
+    }
+
+    #[test]
+    fn glob_to_regex_translates_wildcards() {
+        assert_eq!(glob_to_regex("*"), "^.*$");
+        assert_eq!(glob_to_regex("a?c"), "^a.c$");
+        assert_eq!(glob_to_regex("a.c"), r"^a\.c$");
+    }
+
+    #[test]
+    fn matcher_contains_matches_substrings() {
+        let matcher = Matcher::new(vec!["mouse".to_owned()], MatchMode::Contains).unwrap();
+        assert!(matcher.matches(&device("1-2", "Logitech USB Optical Mouse")));
+        assert!(!matcher.matches(&device("1-2", "SanDisk Cruzer Blade")));
+    }
+
+    #[test]
+    fn matcher_exact_requires_full_match() {
+        let matcher = Matcher::new(vec!["Mouse".to_owned()], MatchMode::Exact).unwrap();
+        assert!(!matcher.matches(&device("1-2", "Logitech USB Optical Mouse")));
+        assert!(matcher.matches(&device("1-2", "Mouse")));
+    }
+
+    #[test]
+    fn matcher_regex_matches_port_or_name() {
+        let matcher = Matcher::new(vec!["^1-.*".to_owned()], MatchMode::Regex).unwrap();
+        assert!(matcher.matches(&device("1-2", "Anything")));
+        assert!(!matcher.matches(&device("2-1", "Anything")));
+    }
+
+    #[test]
+    fn matcher_glob_matches_whole_name() {
+        let matcher = Matcher::new(vec!["*Mouse".to_owned()], MatchMode::Glob).unwrap();
+        assert!(matcher.matches(&device("1-2", "Logitech USB Optical Mouse")));
+        assert!(!matcher.matches(&device("1-2", "Mouse Pad")));
+    }
+}
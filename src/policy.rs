@@ -0,0 +1,271 @@
+//! Declarative allow/deny policy, loaded from a rules file.
+//!
+//! Rules are matched top-to-bottom, much like the allow/deny device rules of
+//! the cgroups `devices` controller: the first rule whose predicate matches a
+//! device decides its [Effect], and a device matched by no rule falls back to
+//! the policy's configured default.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use snafu::{ResultExt, Snafu};
+
+use crate::{
+    device::Device,
+    matcher::{MatchMode, Matcher},
+};
+
+/// Default location of the rules file.
+pub const DEFAULT_PATH: &str = "/etc/usbctl/rules.toml";
+
+/// What to do with a device matched by a [Rule].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effect {
+    /// Keep (or put) the device bound to its driver.
+    Allow,
+    /// Unbind the device from its driver.
+    Deny,
+}
+
+/// A single match predicate, as written in the rules file.
+///
+/// `search` and `mode` back onto the same [Matcher] that powers the CLI's
+/// search options, so a rule can match by substring, exact string, regex or
+/// glob, not just exact equality; `vid`/`pid`/`serial` are ANDed on top. A
+/// rule with `search` empty and every other field unset matches every
+/// device.
+#[derive(Debug, Default, Deserialize)]
+pub struct Match {
+    /// Search terms, matched against the device's port or name.
+    #[serde(default)]
+    pub search: Vec<String>,
+    /// How `search` terms are matched. Defaults to substring matching.
+    #[serde(default)]
+    pub mode: MatchMode,
+    /// Matches the vendor id, case-insensitively.
+    pub vid: Option<String>,
+    /// Matches the product id, case-insensitively.
+    pub pid: Option<String>,
+    /// Matches the serial number exactly.
+    pub serial: Option<String>,
+}
+
+/// A [Match] compiled into a ready-to-evaluate predicate.
+struct CompiledMatch {
+    matcher: Option<Matcher>,
+    vid: Option<String>,
+    pid: Option<String>,
+    serial: Option<String>,
+}
+
+impl CompiledMatch {
+    fn compile(m: Match) -> Result<Self, Error> {
+        let matcher = if m.search.is_empty() {
+            None
+        } else {
+            Some(Matcher::new(m.search, m.mode).context(InvalidMatcher)?)
+        };
+        Ok(CompiledMatch {
+            matcher,
+            vid: m.vid,
+            pid: m.pid,
+            serial: m.serial,
+        })
+    }
+
+    fn matches(&self, device: &Device) -> bool {
+        if let Some(vid) = &self.vid {
+            if !device.matches_vendor_id(vid) {
+                return false;
+            }
+        }
+        if let Some(pid) = &self.pid {
+            if !device.matches_product_id(pid) {
+                return false;
+            }
+        }
+        if let Some(serial) = &self.serial {
+            if !device.matches_serial(serial) {
+                return false;
+            }
+        }
+        match &self.matcher {
+            Some(matcher) => matcher.matches(device),
+            None => true,
+        }
+    }
+}
+
+/// A single rule as written in the rules file.
+#[derive(Debug, Deserialize)]
+struct RawRule {
+    #[serde(flatten)]
+    matches: Match,
+    effect: Effect,
+}
+
+/// The rules file, before its matchers are compiled.
+#[derive(Debug, Default, Deserialize)]
+struct RawPolicy {
+    #[serde(default = "RawPolicy::default_effect")]
+    default: Effect,
+    #[serde(default)]
+    rules: Vec<RawRule>,
+}
+
+impl RawPolicy {
+    fn default_effect() -> Effect {
+        Effect::Allow
+    }
+}
+
+/// One ordered, compiled entry of a [Policy].
+struct Rule {
+    matches: CompiledMatch,
+    effect: Effect,
+}
+
+/// A loaded, ordered set of rules plus a default effect.
+pub struct Policy {
+    /// Effect applied to a device matched by no rule.
+    pub default: Effect,
+    rules: Vec<Rule>,
+}
+
+impl Policy {
+    /// Loads a [Policy] from a TOML file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).context(Read { path })?;
+        let raw: RawPolicy = toml::from_str(&contents).context(Parse { path })?;
+        let rules = raw
+            .rules
+            .into_iter()
+            .map(|rule| {
+                Ok(Rule {
+                    matches: CompiledMatch::compile(rule.matches)?,
+                    effect: rule.effect,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Policy {
+            default: raw.default,
+            rules,
+        })
+    }
+
+    /// Evaluates the policy against `device`, returning the effect of the
+    /// first matching rule, or the policy's default.
+    pub fn evaluate(&self, device: &Device) -> Effect {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches.matches(device))
+            .map_or(self.default, |rule| rule.effect)
+    }
+}
+
+/// Policy loading error.
+#[derive(Debug, Snafu)]
+pub enum Error {
+    /// Unable to read the rules file.
+    #[snafu(display("Unable to read rules file {}", path.display()))]
+    Read {
+        /// Rules file path.
+        path: PathBuf,
+        /// Source error.
+        source: std::io::Error,
+    },
+
+    /// Unable to parse the rules file.
+    #[snafu(display("Unable to parse rules file {}", path.display()))]
+    Parse {
+        /// Rules file path.
+        path: PathBuf,
+        /// Source error.
+        source: toml::de::Error,
+    },
+
+    /// A rule's `search` terms failed to compile (e.g. an invalid regex).
+    #[snafu(display("Invalid rule search terms"))]
+    InvalidMatcher {
+        /// Source error.
+        source: crate::matcher::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::Status;
+    use std::ffi::OsString;
+
+    fn device(port: &str, name: &str) -> Device {
+        Device {
+            port: OsString::from(port),
+            name: name.to_owned(),
+            online: Status::Online,
+            vendor_id: None,
+            product_id: None,
+            manufacturer: None,
+            serial: None,
+            device_class: None,
+        }
+    }
+
+    fn rule(search: &str, effect: Effect) -> RawRule {
+        RawRule {
+            matches: Match {
+                search: vec![search.to_owned()],
+                mode: MatchMode::Contains,
+                vid: None,
+                pid: None,
+                serial: None,
+            },
+            effect,
+        }
+    }
+
+    fn policy(default: Effect, rules: Vec<RawRule>) -> Policy {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                Rule {
+                    matches: CompiledMatch::compile(rule.matches).unwrap(),
+                    effect: rule.effect,
+                }
+            })
+            .collect();
+        Policy { default, rules }
+    }
+
+    #[test]
+    fn evaluate_falls_back_to_default_when_no_rule_matches() {
+        let policy = policy(Effect::Deny, vec![rule("mouse", Effect::Allow)]);
+        assert_eq!(policy.evaluate(&device("1-2", "Keyboard")), Effect::Deny);
+    }
+
+    #[test]
+    fn evaluate_picks_first_matching_rule() {
+        let policy = policy(
+            Effect::Deny,
+            vec![rule("Mouse", Effect::Allow), rule("USB", Effect::Deny)],
+        );
+        // Both rules match "Logitech USB Optical Mouse"; the first one wins.
+        assert_eq!(
+            policy.evaluate(&device("1-2", "Logitech USB Optical Mouse")),
+            Effect::Allow
+        );
+    }
+
+    #[test]
+    fn evaluate_matches_on_vid() {
+        let mut rule = rule("nonexistent", Effect::Allow);
+        rule.matches.search.clear();
+        rule.matches.vid = Some("046d".to_owned());
+        let policy = policy(Effect::Deny, vec![rule]);
+        let mut device = device("1-2", "Anything");
+        device.vendor_id = Some("046d".to_owned());
+        assert_eq!(policy.evaluate(&device), Effect::Allow);
+    }
+}
@@ -0,0 +1,284 @@
+//! Hotplug watch mode.
+//!
+//! Listens for kernel USB [uevents](https://docs.kernel.org/driver-api/usb/hotplug.html)
+//! over a `NETLINK_KOBJECT_UEVENT` socket and re-applies an [Action] to every
+//! newly attached device that matches a [Filter], instead of acting once over
+//! a single [device::discover] pass.
+
+use std::{collections::HashMap, os::unix::io::RawFd, time::Duration};
+
+use nix::{
+    poll::{poll, PollFd, PollFlags},
+    sys::socket::{
+        bind, recv, socket, AddressFamily, MsgFlags, NetlinkAddr, SockAddr, SockFlag,
+        SockProtocol, SockType,
+    },
+};
+use snafu::{ResultExt, Snafu};
+
+use crate::{
+    actions::{self, Action, Apply, Filter, FilterExt},
+    device::{self, Device},
+};
+
+/// Multicast group of the kernel `udev` uevent source.
+const KOBJECT_UEVENT_GROUP: u32 = 1;
+
+/// Minimum time between two reactions to events on the same `DEVPATH`, so a
+/// burst of uevents for one enumeration doesn't trigger the action repeatedly.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watcher setup and runtime error.
+#[derive(Debug, Snafu)]
+pub enum Error {
+    /// Unable to open the netlink socket.
+    #[snafu(display("Unable to open a netlink socket"))]
+    Open {
+        /// Source error.
+        source: nix::Error,
+    },
+
+    /// Unable to bind the netlink socket to the uevent multicast group.
+    #[snafu(display("Unable to bind a netlink socket"))]
+    Bind {
+        /// Source error.
+        source: nix::Error,
+    },
+
+    /// Polling the socket for readability failed.
+    #[snafu(display("Unable to poll the netlink socket"))]
+    Poll {
+        /// Source error.
+        source: nix::Error,
+    },
+
+    /// Reading a datagram from the socket failed.
+    #[snafu(display("Unable to read from the netlink socket"))]
+    Recv {
+        /// Source error.
+        source: nix::Error,
+    },
+
+    /// Re-discovering devices after an event failed.
+    #[snafu(display("Looking for devices"))]
+    Discover {
+        /// Source error.
+        source: device::DirectoryOpenError,
+    },
+
+    /// Applying the configured action failed.
+    #[snafu(display("Applying an action"))]
+    Apply {
+        /// Source error.
+        source: actions::Error,
+    },
+}
+
+/// A parsed kernel uevent, as found on the `NETLINK_KOBJECT_UEVENT` socket.
+///
+/// Each message is a sequence of `\0`-separated `KEY=value` lines.
+#[derive(Debug)]
+struct UEvent {
+    action: String,
+    subsystem: Option<String>,
+    devpath: Option<String>,
+}
+
+impl UEvent {
+    /// Parses a raw uevent datagram, keeping only the fields we care about.
+    fn parse(raw: &[u8]) -> Option<Self> {
+        let mut action = None;
+        let mut subsystem = None;
+        let mut devpath = None;
+        for line in raw.split(|&b| b == 0) {
+            let line = std::str::from_utf8(line).ok()?;
+            if let Some(value) = line.strip_prefix("ACTION=") {
+                action = Some(value.to_owned());
+            } else if let Some(value) = line.strip_prefix("SUBSYSTEM=") {
+                subsystem = Some(value.to_owned());
+            } else if let Some(value) = line.strip_prefix("DEVPATH=") {
+                devpath = Some(value.to_owned());
+            }
+        }
+        Some(UEvent {
+            action: action?,
+            subsystem,
+            devpath,
+        })
+    }
+
+    /// Whether this event is a USB device becoming available: either the
+    /// device node showing up (`add`) or the driver binding to it (`bind`).
+    fn is_relevant(&self) -> bool {
+        self.subsystem.as_deref() == Some("usb") && matches!(self.action.as_str(), "add" | "bind")
+    }
+
+    /// Derives the USB port from the `DEVPATH` basename, e.g.
+    /// `/devices/pci0000:00/.../usb1/1-2` yields `1-2`.
+    fn port(&self) -> Option<&str> {
+        self.devpath.as_deref()?.rsplit('/').next()
+    }
+}
+
+/// A [Filter] that only accepts the device plugged into the given `port`.
+struct PortFilter(String);
+
+impl Filter for PortFilter {
+    fn filter(&mut self, device: &Device) -> bool {
+        device.port.to_string_lossy() == self.0
+    }
+}
+
+/// Watches for USB hotplug events and re-applies `action` to devices matching
+/// `filter` whenever a new one shows up.
+pub struct Watcher<F> {
+    socket: RawFd,
+    filter: F,
+    action: Action,
+    dry_run: bool,
+    last_seen: HashMap<String, std::time::Instant>,
+}
+
+impl<F> Watcher<F>
+where
+    F: Filter,
+{
+    /// Opens the netlink socket and builds a [Watcher] applying `action` to
+    /// devices accepted by `filter`.
+    pub fn new(filter: F, action: Action, dry_run: bool) -> Result<Self, Error> {
+        let socket_fd = socket(
+            AddressFamily::Netlink,
+            SockType::Raw,
+            SockFlag::empty(),
+            SockProtocol::NetlinkKObjectUEvent,
+        )
+        .context(Open)?;
+        bind(socket_fd, &SockAddr::Netlink(NetlinkAddr::new(0, KOBJECT_UEVENT_GROUP)))
+            .context(Bind)?;
+        Ok(Watcher {
+            socket: socket_fd,
+            filter,
+            action,
+            dry_run,
+            last_seen: HashMap::new(),
+        })
+    }
+
+    /// Runs the watch loop until interrupted (e.g. by `SIGINT`), reacting to
+    /// every relevant `add` or `bind` event.
+    ///
+    /// The poll is blocking but interruptible, so `^C` terminates the loop
+    /// promptly instead of waiting for the next uevent.
+    pub fn run(mut self) -> Result<(), Error> {
+        let mut buf = [0u8; 4096];
+        loop {
+            let mut fds = [PollFd::new(self.socket, PollFlags::POLLIN)];
+            match poll(&mut fds, -1) {
+                Ok(_) => {}
+                Err(nix::Error::Sys(nix::errno::Errno::EINTR)) => continue,
+                Err(source) => return Err(Error::Poll { source }),
+            }
+            let len = match recv(self.socket, &mut buf, MsgFlags::empty()) {
+                Ok(len) => len,
+                Err(nix::Error::Sys(nix::errno::Errno::EINTR)) => continue,
+                Err(source) => return Err(Error::Recv { source }),
+            };
+            let event = match UEvent::parse(&buf[..len]) {
+                Some(event) => event,
+                None => continue,
+            };
+            if !event.is_relevant() {
+                continue;
+            }
+            let port = match event.port() {
+                Some(port) => port.to_owned(),
+                None => continue,
+            };
+            if self.debounced(&port) {
+                log::debug!("Debounced hotplug event for port {}", port);
+                continue;
+            }
+            log::debug!("Handling hotplug event for port {}", port);
+            if let Err(e) = self.react(&port) {
+                log::warn!("Unable to react to a hotplug event: {}", e);
+            }
+        }
+    }
+
+    /// Returns `true` if an event for `port` was already handled too
+    /// recently.
+    fn debounced(&mut self, port: &str) -> bool {
+        let now = std::time::Instant::now();
+        if let Some(last) = self.last_seen.get(port) {
+            if now.duration_since(*last) < DEBOUNCE {
+                return true;
+            }
+        }
+        self.last_seen.insert(port.to_owned(), now);
+        false
+    }
+
+    /// Re-discovers devices and applies `action` to the device at `port`, if
+    /// it also matches `filter`.
+    fn react(&mut self, port: &str) -> Result<(), Error> {
+        let devices = device::discover().context(Discover)?;
+        Apply::new(devices)
+            .filter(PortFilter(port.to_owned()).chain(&mut self.filter))
+            .dry_run(self.dry_run)
+            .run(self.action)
+            .context(Apply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(fields: &[&str]) -> Vec<u8> {
+        fields.join("\0").into_bytes()
+    }
+
+    #[test]
+    fn parse_extracts_known_fields() {
+        let event = UEvent::parse(&raw(&[
+            "ACTION=add",
+            "SUBSYSTEM=usb",
+            "DEVPATH=/devices/pci0000:00/0000:00:14.0/usb1/1-2",
+        ]))
+        .unwrap();
+        assert_eq!(event.action, "add");
+        assert_eq!(event.subsystem.as_deref(), Some("usb"));
+        assert_eq!(event.port(), Some("1-2"));
+    }
+
+    #[test]
+    fn parse_ignores_unknown_fields_and_requires_action() {
+        assert!(UEvent::parse(&raw(&["SUBSYSTEM=usb", "SOMETHING=else"])).is_none());
+
+        let event = UEvent::parse(&raw(&["ACTION=add", "SOMETHING=else"])).unwrap();
+        assert_eq!(event.action, "add");
+        assert_eq!(event.subsystem, None);
+        assert_eq!(event.devpath, None);
+    }
+
+    #[test]
+    fn is_relevant_requires_usb_subsystem_and_add_or_bind_action() {
+        let relevant = UEvent::parse(&raw(&["ACTION=add", "SUBSYSTEM=usb"])).unwrap();
+        assert!(relevant.is_relevant());
+
+        let bound = UEvent::parse(&raw(&["ACTION=bind", "SUBSYSTEM=usb"])).unwrap();
+        assert!(bound.is_relevant());
+
+        let wrong_subsystem = UEvent::parse(&raw(&["ACTION=add", "SUBSYSTEM=net"])).unwrap();
+        assert!(!wrong_subsystem.is_relevant());
+
+        let wrong_action = UEvent::parse(&raw(&["ACTION=remove", "SUBSYSTEM=usb"])).unwrap();
+        assert!(!wrong_action.is_relevant());
+    }
+
+    #[test]
+    fn port_is_none_without_devpath() {
+        let event = UEvent::parse(&raw(&["ACTION=add", "SUBSYSTEM=usb"])).unwrap();
+        assert_eq!(event.port(), None);
+    }
+}